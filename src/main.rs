@@ -1,19 +1,32 @@
-use std::path::PathBuf;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, UNIX_EPOCH};
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use chrono::{Local, NaiveTime, Timelike};
+use clap::{Parser, Subcommand};
+use ignore::{WalkBuilder, WalkState};
+use image::GenericImageView;
 use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
 use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::mpsc;
 use tokio::time::sleep;
-use walkdir::WalkDir;
+
+/// An image assigned to a time-of-day slot, sorted ascending by `start`.
+type Schedule = Vec<(NaiveTime, PathBuf)>;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
 struct Cli {
-    /// Paths to the images to be used for setting the wallpaper
-    #[clap(short, long, required = true, value_name = "IMAGE_PATHS")]
+    /// Paths to the images to be used for setting the wallpaper. Required
+    /// unless a control subcommand (e.g. `status`) is given.
+    #[clap(short, long, value_name = "IMAGE_PATHS")]
     image_paths: Vec<PathBuf>,
 
     /// Interval in seconds to change the wallpaper
@@ -27,15 +40,96 @@ struct Cli {
     /// Transition duration in seconds
     #[clap(short('d'), long, default_value = "3", value_name="TRANSITION_DURATION_SECS")]
     transition_duration_secs: u32,
+
+    /// Set a different random image on each connected output instead of one
+    /// global wallpaper
+    #[clap(long)]
+    per_monitor: bool,
+
+    /// Map images to times of day instead of picking randomly
+    #[clap(long)]
+    time_based: bool,
+
+    /// Optional `HH:MM=path` schedule file for --time-based. Without this,
+    /// discovered images are sorted lexicographically and spread evenly
+    /// across the 24 hour day.
+    #[clap(long, value_name = "SCHEDULE_FILE", requires = "time_based")]
+    schedule: Option<PathBuf>,
+
+    /// Include hidden files and directories during image discovery
+    #[clap(long)]
+    hidden: bool,
+
+    /// Don't respect .gitignore/.ignore rules during image discovery
+    #[clap(long)]
+    no_ignore: bool,
+
+    /// Drop visually near-duplicate images from the discovered pool
+    #[clap(long)]
+    dedupe: bool,
+
+    /// Maximum Hamming distance between dHash fingerprints for two images
+    /// to be considered near-duplicates
+    #[clap(
+        long,
+        default_value = "5",
+        value_name = "DEDUPE_THRESHOLD",
+        requires = "dedupe"
+    )]
+    dedupe_threshold: u32,
+
+    /// Avoid repeating any of the last N shown images until the pool is
+    /// exhausted, in addition to skipping the immediately-current one
+    #[clap(long, default_value = "0", value_name = "COUNT")]
+    no_repeat_last: usize,
+
+    /// On startup, re-apply the most recently applied wallpaper (persisted
+    /// across restarts) instead of picking a new one. Ignored with
+    /// --per-monitor.
+    #[clap(long)]
+    reapply_last: bool,
+
+    /// Path to the daemon's control socket. Defaults to
+    /// `$XDG_RUNTIME_DIR/wall-switch.sock`.
+    #[clap(long, value_name = "SOCKET_PATH")]
+    socket_path: Option<PathBuf>,
+
+    /// Send a control command to an already-running daemon instead of
+    /// starting a new one
+    #[clap(subcommand)]
+    command: Option<ControlCommand>,
 }
 
-/// Recursively discover all image files from the given folder paths
-fn discover_images(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
-    let mut images = Vec::new();
+/// Control commands sent over the daemon's Unix socket by a sibling
+/// invocation of this same binary.
+#[derive(Subcommand, Debug)]
+enum ControlCommand {
+    /// Advance to the next wallpaper immediately, like sending SIGUSR1
+    Next,
+    /// Force a specific image, which must be part of the running daemon's
+    /// discovered pool
+    Set {
+        /// Path to the image to force
+        path: PathBuf,
+    },
+    /// Suspend the interval/schedule timer
+    Pause,
+    /// Resume the interval/schedule timer
+    Resume,
+    /// Re-set the most recently applied wallpaper
+    ReapplyLast,
+    /// Report the current wallpaper, pool size, and seconds until next change
+    Status,
+}
 
+/// Recursively discover all image files from the given folder paths,
+/// walking all roots concurrently (one worker per CPU) and honoring
+/// .gitignore/hidden-file rules unless overridden via `--hidden`/`--no-ignore`
+fn discover_images(paths: &[PathBuf], cli: &Cli) -> Result<Vec<PathBuf>> {
     // Common image extensions to look for
     let image_extensions = ["jpg", "jpeg", "png", "gif", "bmp", "webp", "tiff", "tif"];
 
+    let mut valid_paths = Vec::new();
     for path in paths {
         if !path.exists() {
             eprintln!("Warning: Path does not exist: {}", path.display());
@@ -47,36 +141,59 @@ fn discover_images(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
             continue;
         }
 
-        // Walk through the directory recursively
-        for entry in WalkDir::new(path).follow_links(true) {
-            match entry {
-                Ok(entry) => {
-                    let path = entry.path();
-
-                    // Check if it's a file and has an image extension
-                    if path.is_file()
-                        && let Some(extension) = path.extension()
-                        && let Some(ext_str) = extension.to_str()
-                    {
-                        let ext_lower = ext_str.to_lowercase();
-                        if image_extensions.contains(&ext_lower.as_str()) {
-                            images.push(path.to_path_buf());
-                        }
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Error accessing file: {}", e);
+        valid_paths.push(path);
+    }
+
+    let Some((first, rest)) = valid_paths.split_first() else {
+        println!("Discovered 0 images");
+        return Ok(Vec::new());
+    };
+
+    let mut builder = WalkBuilder::new(first);
+    for path in rest {
+        builder.add(path);
+    }
+    builder
+        .threads(std::thread::available_parallelism().map_or(0, |n| n.get()))
+        .follow_links(true)
+        .hidden(!cli.hidden)
+        .ignore(!cli.no_ignore)
+        .git_ignore(!cli.no_ignore)
+        .git_global(!cli.no_ignore)
+        .git_exclude(!cli.no_ignore);
+
+    let (sender, receiver) = crossbeam_channel::unbounded();
+
+    builder.build_parallel().run(|| {
+        let sender = sender.clone();
+        Box::new(move |entry| {
+            if let Ok(entry) = entry
+                && entry.file_type().is_some_and(|file_type| file_type.is_file())
+                && let Some(extension) = entry.path().extension()
+                && let Some(ext_str) = extension.to_str()
+            {
+                let ext_lower = ext_str.to_lowercase();
+                if image_extensions.contains(&ext_lower.as_str()) {
+                    let _ = sender.send(entry.into_path());
                 }
             }
-        }
-    }
+
+            WalkState::Continue
+        })
+    });
+
+    // Drop the original sender so the receiver's iterator ends once every
+    // worker thread's cloned sender has also been dropped.
+    drop(sender);
+    let images: Vec<PathBuf> = receiver.into_iter().collect();
 
     println!("Discovered {} images", images.len());
     Ok(images)
 }
 
-/// Query the current wallpaper using `swww query`
-fn get_current_wallpaper() -> Result<Option<PathBuf>> {
+/// Query `swww query` and parse the currently displayed image for every
+/// output, keeping the `DP-1:`-style monitor name alongside each one.
+fn get_current_wallpapers() -> Result<Vec<(String, Option<PathBuf>)>> {
     let output = Command::new("swww")
         .arg("query")
         .output()
@@ -89,17 +206,45 @@ fn get_current_wallpaper() -> Result<Option<PathBuf>> {
 
     let stdout = String::from_utf8_lossy(&output.stdout);
 
-    // Parse output like: "DP-1: 2560x1080, scale: 2, currently displaying: image: /path/to/image.jpg"
+    // Parse lines like: "DP-1: 2560x1080, scale: 2, currently displaying: image: /path/to/image.jpg"
+    let mut wallpapers = Vec::new();
     for line in stdout.lines() {
-        if let Some(image_part) = line.split("currently displaying: image: ").nth(1) {
-            return Ok(Some(PathBuf::from(image_part.trim())));
-        }
+        let Some((name, rest)) = line.split_once(':') else {
+            continue;
+        };
+
+        let image = rest
+            .split("currently displaying: image: ")
+            .nth(1)
+            .map(|image_part| PathBuf::from(image_part.trim()));
+
+        wallpapers.push((name.trim().to_string(), image));
     }
 
-    Ok(None)
+    Ok(wallpapers)
 }
-/// Perform one wallpaper change cycle: query current, pick a different random image, and set it
-fn change_wallpaper_once(images: &[PathBuf], cli: &Cli) {
+
+/// Query the current wallpaper using `swww query`
+fn get_current_wallpaper() -> Result<Option<PathBuf>> {
+    Ok(get_current_wallpapers()?
+        .into_iter()
+        .find_map(|(_, image)| image))
+}
+
+/// Perform one wallpaper change cycle: query current, pick the next image
+/// (random, or time-based when `schedule` is set), and set it. `history`
+/// tracks recently-shown images so a random pick can avoid repeats.
+fn change_wallpaper_once(
+    images: &[PathBuf],
+    schedule: Option<&Schedule>,
+    history: &mut VecDeque<PathBuf>,
+    cli: &Cli,
+) {
+    if cli.per_monitor {
+        change_wallpaper_once_per_monitor(images, schedule, history, cli);
+        return;
+    }
+
     // Get current wallpaper
     let current_wallpaper = match get_current_wallpaper() {
         Ok(current) => {
@@ -114,28 +259,125 @@ fn change_wallpaper_once(images: &[PathBuf], cli: &Cli) {
         }
     };
 
-    // Select a new random wallpaper
-    if let Some(new_wallpaper) = select_random_image(images, current_wallpaper.as_ref()) {
+    let new_wallpaper = match schedule {
+        Some(schedule) => image_for_time(schedule, Local::now().time()),
+        None => select_random_image(images, current_wallpaper.as_ref(), history),
+    };
+
+    if let Some(new_wallpaper) = new_wallpaper {
         // Only change if it's different from current (extra safety check)
         if current_wallpaper.as_ref() != Some(&new_wallpaper) {
-            if let Err(e) = set_wallpaper(&new_wallpaper, cli) {
+            if let Err(e) = set_wallpaper(&new_wallpaper, None, cli) {
                 eprintln!("Error setting wallpaper: {}", e);
+            } else {
+                remember_shown(history, new_wallpaper, cli.no_repeat_last);
             }
         } else {
             println!("Selected image is the same as current, skipping change");
         }
     } else {
-        eprintln!("Warning: Could not select a random image");
+        eprintln!("Warning: Could not select an image to display");
     }
 }
 
+/// Per-output variant of [`change_wallpaper_once`]: query every connected
+/// output and assign each one an image, skipping an output if the picked
+/// image matches what it's already displaying. In time-based mode every
+/// output is assigned the same image for the current time slot; otherwise
+/// each output gets a distinct random image, unless the current, already
+/// assigned and recently shown images cover the whole pool, in which case
+/// the pick falls back to the full pool and may repeat another output's.
+fn change_wallpaper_once_per_monitor(
+    images: &[PathBuf],
+    schedule: Option<&Schedule>,
+    history: &mut VecDeque<PathBuf>,
+    cli: &Cli,
+) {
+    let outputs = match get_current_wallpapers() {
+        Ok(outputs) => outputs,
+        Err(e) => {
+            eprintln!("Warning: Could not query current wallpapers: {}", e);
+            return;
+        }
+    };
+
+    if outputs.is_empty() {
+        eprintln!("Warning: swww query reported no outputs");
+        return;
+    }
 
-/// Set wallpaper using `swww img`
-fn set_wallpaper(image_path: &PathBuf, cli: &Cli) -> Result<()> {
-    println!("Setting wallpaper to: {}", image_path.display());
+    let time_based_image = schedule.and_then(|schedule| image_for_time(schedule, Local::now().time()));
 
-    let output = Command::new("swww")
-        .arg("img")
+    // Images already assigned this cycle, excluded so that outputs get
+    // distinct wallpapers while the pool has unexcluded images. Unused in
+    // time-based mode since every output wants the same image.
+    let mut assigned: Vec<PathBuf> = Vec::new();
+
+    for (output_name, current_wallpaper) in outputs {
+        if let Some(ref path) = current_wallpaper {
+            println!("Current wallpaper on {}: {}", output_name, path.display());
+        }
+
+        let new_wallpaper = if schedule.is_some() {
+            let Some(image) = time_based_image.clone() else {
+                eprintln!(
+                    "Warning: Could not select a time-based image for output {}",
+                    output_name
+                );
+                continue;
+            };
+            image
+        } else {
+            let excluded: Vec<&PathBuf> = current_wallpaper
+                .iter()
+                .chain(assigned.iter())
+                .chain(history.iter())
+                .collect();
+            let Some(image) = select_random_image_excluding(images, &excluded) else {
+                eprintln!(
+                    "Warning: Could not select a random image for output {}",
+                    output_name
+                );
+                continue;
+            };
+            image
+        };
+
+        if current_wallpaper.as_ref() == Some(&new_wallpaper) {
+            println!(
+                "Selected image for {} is the same as current, skipping change",
+                output_name
+            );
+            assigned.push(new_wallpaper);
+            continue;
+        }
+
+        if let Err(e) = set_wallpaper(&new_wallpaper, Some(output_name.as_str()), cli) {
+            eprintln!("Error setting wallpaper on {}: {}", output_name, e);
+            continue;
+        }
+
+        remember_shown(history, new_wallpaper.clone(), cli.no_repeat_last);
+        assigned.push(new_wallpaper);
+    }
+}
+
+/// Set wallpaper using `swww img`, optionally restricting it to a single
+/// output via `--outputs`.
+fn set_wallpaper(image_path: &PathBuf, output: Option<&str>, cli: &Cli) -> Result<()> {
+    match output {
+        Some(output) => println!("Setting wallpaper on {} to: {}", output, image_path.display()),
+        None => println!("Setting wallpaper to: {}", image_path.display()),
+    }
+
+    let mut command = Command::new("swww");
+    command.arg("img");
+
+    if let Some(output) = output {
+        command.arg("--outputs").arg(output);
+    }
+
+    let output = command
         .arg("--transition-type")
         .arg(&cli.transition_type)
         .arg("--transition-duration")
@@ -150,71 +392,953 @@ fn set_wallpaper(image_path: &PathBuf, cli: &Cli) -> Result<()> {
     }
 
     println!("Wallpaper changed successfully");
+
+    if let Err(e) = persist_last_wallpaper(image_path) {
+        eprintln!("Warning: Could not persist last wallpaper state: {}", e);
+    }
+
     Ok(())
 }
 
-/// Select a random image that's different from the current wallpaper
-fn select_random_image(images: &[PathBuf], current: Option<&PathBuf>) -> Option<PathBuf> {
+/// Path to the small state file under the XDG cache dir that remembers the
+/// most recently applied wallpaper, for `--reapply-last`.
+fn last_wallpaper_state_path() -> Result<PathBuf> {
+    Ok(cache_dir()?.join("last-wallpaper"))
+}
+
+fn persist_last_wallpaper(image_path: &Path) -> Result<()> {
+    let path = last_wallpaper_state_path()?;
+    std::fs::write(&path, image_path.to_string_lossy().as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Load the most recently applied wallpaper persisted by a prior run, if any
+fn load_last_wallpaper() -> Option<PathBuf> {
+    let path = last_wallpaper_state_path().ok()?;
+    let contents = std::fs::read_to_string(path).ok()?;
+    let trimmed = contents.trim();
+    (!trimmed.is_empty()).then(|| PathBuf::from(trimmed))
+}
+
+/// Push `image` onto the no-repeat history ring buffer, trimming it down to
+/// `capacity` entries. A `capacity` of 0 disables the history window
+/// entirely (only the immediately-current image is avoided).
+fn remember_shown(history: &mut VecDeque<PathBuf>, image: PathBuf, capacity: usize) {
+    if capacity == 0 {
+        return;
+    }
+
+    history.push_back(image);
+    while history.len() > capacity {
+        history.pop_front();
+    }
+}
+
+/// Select a random image that's none of `current` or the no-repeat `history`
+fn select_random_image(
+    images: &[PathBuf],
+    current: Option<&PathBuf>,
+    history: &VecDeque<PathBuf>,
+) -> Option<PathBuf> {
+    let excluded: Vec<&PathBuf> = current.into_iter().chain(history.iter()).collect();
+    select_random_image_excluding(images, &excluded)
+}
+
+/// Select a random image that's none of `excluded`, falling back to the
+/// full pool if everything is excluded.
+fn select_random_image_excluding(images: &[PathBuf], excluded: &[&PathBuf]) -> Option<PathBuf> {
     let mut rng = rand::rng();
 
-    // If there's only one image or no current wallpaper, just pick randomly
-    if images.len() <= 1 || current.is_none() {
+    if excluded.is_empty() {
         return images.choose(&mut rng).cloned();
     }
 
-    let current = current.unwrap();
+    let candidates: Vec<&PathBuf> = images
+        .iter()
+        .filter(|img| !excluded.contains(img))
+        .collect();
 
-    // Filter out the current wallpaper and pick from the rest
-    let candidates: Vec<&PathBuf> = images.iter().filter(|&img| img != current).collect();
-
-    // If all images are the same as current (shouldn't happen), just return current
+    // If every image is excluded (e.g. the no-repeat window covers the whole
+    // pool), fall back to picking from the full pool so we still return
+    // something.
     if candidates.is_empty() {
-        return Some(current.clone());
+        return images.choose(&mut rng).cloned();
     }
 
     candidates.choose(&mut rng).map(|&img| img.clone())
 }
 
+/// A cached dHash fingerprint for an image, keyed by path + mtime so
+/// unchanged files don't need to be re-hashed on the next run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ImageFingerprint {
+    mtime_secs: i64,
+    /// `None` means the image could not be decoded/hashed; such images are
+    /// kept in the pool as their own singleton group and never merged with
+    /// anything else.
+    hash: Option<u64>,
+    width: u32,
+    height: u32,
+}
+
+type FingerprintCache = HashMap<String, ImageFingerprint>;
+
+/// Directory under the XDG cache dir used for wall-switch's own caches,
+/// created on demand.
+fn cache_dir() -> Result<PathBuf> {
+    let dir = dirs::cache_dir()
+        .context("Could not determine XDG cache directory")?
+        .join("wall-switch");
+
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create cache directory: {}", dir.display()))?;
+
+    Ok(dir)
+}
+
+fn load_fingerprint_cache(path: &Path) -> FingerprintCache {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_fingerprint_cache(path: &Path, cache: &FingerprintCache) -> Result<()> {
+    let contents =
+        serde_json::to_string(cache).context("Failed to serialize fingerprint cache")?;
+    std::fs::write(path, contents)
+        .with_context(|| format!("Failed to write fingerprint cache: {}", path.display()))?;
+    Ok(())
+}
+
+/// Compute a 64-bit difference hash (dHash) for an image: grayscale,
+/// resize to 9x8, then for each row set a bit wherever a pixel is
+/// brighter than its right-hand neighbor.
+fn compute_dhash(image_path: &Path) -> Result<u64> {
+    let image = image::open(image_path)
+        .with_context(|| format!("Failed to decode image: {}", image_path.display()))?
+        .grayscale()
+        .resize_exact(9, 8, image::imageops::FilterType::Triangle);
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = image.get_pixel(x, y)[0];
+            let right = image.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+
+    Ok(hash)
+}
+
+fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Group fingerprints into near-duplicate clusters, keeping only the
+/// highest-resolution representative of each. Two fingerprints merge when
+/// both have a hash and that hash's Hamming distance is within `threshold`
+/// bits (inclusive); unhashable fingerprints (`hash: None`) never merge and
+/// are kept as their own singleton group.
+fn group_by_hash(
+    fingerprints: Vec<(PathBuf, ImageFingerprint)>,
+    threshold: u32,
+) -> Vec<(PathBuf, ImageFingerprint)> {
+    let mut kept: Vec<(PathBuf, ImageFingerprint)> = Vec::new();
+    for (path, fingerprint) in fingerprints {
+        match kept.iter_mut().find(|(_, kept_fp)| {
+            match (kept_fp.hash, fingerprint.hash) {
+                (Some(a), Some(b)) => hamming_distance(a, b) <= threshold,
+                _ => false,
+            }
+        }) {
+            Some((kept_path, kept_fp)) => {
+                if fingerprint.width * fingerprint.height > kept_fp.width * kept_fp.height {
+                    *kept_path = path;
+                    *kept_fp = fingerprint;
+                }
+            }
+            None => kept.push((path, fingerprint)),
+        }
+    }
+    kept
+}
+
+/// Drop visually near-duplicate images, keeping the highest-resolution
+/// representative of each group of images whose dHash fingerprints are
+/// within `threshold` bits of each other (inclusive, so a `threshold` of 0
+/// still merges byte-identical dHashes). Images that fail to hash are kept
+/// as their own singleton group rather than dropped. Fingerprints are cached on disk
+/// keyed by path + mtime so unchanged files aren't re-hashed on startup.
+fn dedupe_images(images: Vec<PathBuf>, threshold: u32) -> Result<Vec<PathBuf>> {
+    let original_count = images.len();
+    let cache_path = cache_dir()?.join("dedupe-cache.json");
+    let mut cache = load_fingerprint_cache(&cache_path);
+
+    let mut fingerprints = Vec::with_capacity(images.len());
+    for image_path in images {
+        let mtime_secs = std::fs::metadata(&image_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or(0);
+
+        let key = image_path.to_string_lossy().into_owned();
+        let fingerprint = match cache.get(&key) {
+            Some(cached) if cached.mtime_secs == mtime_secs => cached.clone(),
+            _ => {
+                let hash = match compute_dhash(&image_path) {
+                    Ok(hash) => Some(hash),
+                    Err(e) => {
+                        eprintln!("Warning: Could not hash {}: {}", image_path.display(), e);
+                        None
+                    }
+                };
+                let (width, height) = image::image_dimensions(&image_path).unwrap_or((0, 0));
+                let fingerprint = ImageFingerprint {
+                    mtime_secs,
+                    hash,
+                    width,
+                    height,
+                };
+                // Only cache fingerprints we actually managed to hash, so a
+                // corrupt/unreadable file gets retried on the next run
+                // instead of being permanently remembered as unhashable.
+                if hash.is_some() {
+                    cache.insert(key, fingerprint.clone());
+                }
+                fingerprint
+            }
+        };
+
+        fingerprints.push((image_path, fingerprint));
+    }
+
+    if let Err(e) = save_fingerprint_cache(&cache_path, &cache) {
+        eprintln!("Warning: Could not save fingerprint cache: {}", e);
+    }
+
+    let kept = group_by_hash(fingerprints, threshold);
+
+    println!(
+        "Deduped {} images down to {} after dropping near-duplicates",
+        original_count,
+        kept.len()
+    );
+
+    Ok(kept.into_iter().map(|(path, _)| path).collect())
+}
+
+/// Build a time-of-day schedule: either from an explicit `HH:MM=path` file,
+/// or by sorting `images` lexicographically and spreading them evenly
+/// across the 24 hour day.
+fn build_schedule(images: &[PathBuf], schedule_path: Option<&PathBuf>) -> Result<Schedule> {
+    if let Some(path) = schedule_path {
+        return parse_schedule_file(path);
+    }
+
+    let mut sorted_images = images.to_vec();
+    sorted_images.sort();
+
+    if sorted_images.is_empty() {
+        anyhow::bail!("No images available to build a time-based schedule");
+    }
+
+    let slot_secs = (24 * 60 * 60) / sorted_images.len() as u32;
+    let schedule = sorted_images
+        .into_iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let time = NaiveTime::from_num_seconds_from_midnight_opt(i as u32 * slot_secs, 0)
+                .expect("slot offset is always within a day");
+            (time, path)
+        })
+        .collect();
+
+    Ok(schedule)
+}
+
+/// Parse a schedule file of `HH:MM=path` lines (blank lines and `#`
+/// comments are ignored), sorted ascending by time.
+fn parse_schedule_file(path: &PathBuf) -> Result<Schedule> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read schedule file: {}", path.display()))?;
+
+    let mut schedule = Vec::new();
+    for (line_no, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (time_str, image_path) = line.split_once('=').with_context(|| {
+            format!(
+                "Invalid schedule entry on line {}: expected HH:MM=path",
+                line_no + 1
+            )
+        })?;
+
+        let time = NaiveTime::parse_from_str(time_str.trim(), "%H:%M").with_context(|| {
+            format!(
+                "Invalid time '{}' on line {} of schedule file",
+                time_str.trim(),
+                line_no + 1
+            )
+        })?;
+
+        schedule.push((time, PathBuf::from(image_path.trim())));
+    }
+
+    if schedule.is_empty() {
+        anyhow::bail!("Schedule file {} contained no entries", path.display());
+    }
+
+    schedule.sort_by_key(|(time, _)| *time);
+    Ok(schedule)
+}
+
+/// Pick the image whose slot contains `now`, wrapping around to the last
+/// slot if `now` falls before the first slot of the day.
+fn image_for_time(schedule: &Schedule, now: NaiveTime) -> Option<PathBuf> {
+    schedule
+        .iter()
+        .rev()
+        .find(|(start, _)| *start <= now)
+        .or_else(|| schedule.last())
+        .map(|(_, path)| path.clone())
+}
+
+/// Duration until the next slot boundary after `now`, wrapping to the
+/// first slot of the following day if `now` is past the last slot.
+fn next_slot_boundary(schedule: &Schedule, now: NaiveTime) -> Duration {
+    let now_secs = now.num_seconds_from_midnight() as i64;
+    const DAY_SECS: i64 = 24 * 60 * 60;
+
+    let next_secs = schedule
+        .iter()
+        .map(|(start, _)| start.num_seconds_from_midnight() as i64)
+        .find(|&secs| secs > now_secs)
+        .unwrap_or_else(|| schedule[0].0.num_seconds_from_midnight() as i64 + DAY_SECS);
+
+    Duration::from_secs((next_secs - now_secs) as u64)
+}
+
+/// Commands accepted on the control socket, either as a plain-text line
+/// (`next`, `pause`, `resume`, `status`, `set <path>`) or as a JSON object
+/// (`{"cmd":"set","path":"..."}`).
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum SocketCommand {
+    Next,
+    Set { path: PathBuf },
+    Pause,
+    Resume,
+    ReapplyLast,
+    Status,
+}
+
+impl SocketCommand {
+    fn parse_line(line: &str) -> Result<Self> {
+        let line = line.trim();
+
+        if line.starts_with('{') {
+            return serde_json::from_str(line).context("Invalid JSON control command");
+        }
+
+        let mut parts = line.splitn(2, char::is_whitespace);
+        match parts.next().unwrap_or_default() {
+            "next" => Ok(SocketCommand::Next),
+            "pause" => Ok(SocketCommand::Pause),
+            "resume" => Ok(SocketCommand::Resume),
+            "reapply-last" => Ok(SocketCommand::ReapplyLast),
+            "status" => Ok(SocketCommand::Status),
+            "set" => {
+                let path = parts
+                    .next()
+                    .map(str::trim)
+                    .filter(|path| !path.is_empty())
+                    .context("`set` requires a path argument")?;
+                Ok(SocketCommand::Set {
+                    path: PathBuf::from(path),
+                })
+            }
+            other => anyhow::bail!("Unknown control command: {}", other),
+        }
+    }
+}
+
+/// A parsed control command forwarded from a socket connection to the main
+/// daemon loop.
+#[derive(Debug)]
+enum DaemonCommand {
+    Next,
+    Set(PathBuf),
+    Pause,
+    Resume,
+    ReapplyLast,
+}
+
+/// State shared between the daemon loop and control socket connections so
+/// `status` can be answered without round-tripping through the main loop.
+struct SharedDaemonState {
+    paused: AtomicBool,
+    next_change_at: StdMutex<Option<tokio::time::Instant>>,
+}
+
+/// Resolve the control socket path: the explicit `--socket-path` if given,
+/// otherwise `$XDG_RUNTIME_DIR/wall-switch.sock`.
+fn resolve_socket_path(explicit: Option<&PathBuf>) -> Result<PathBuf> {
+    if let Some(path) = explicit {
+        return Ok(path.clone());
+    }
+
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .context("XDG_RUNTIME_DIR is not set; pass --socket-path explicitly")?;
+
+    Ok(runtime_dir.join("wall-switch.sock"))
+}
+
+/// Connect to an already-running daemon's control socket, send `command`,
+/// and print its response.
+async fn send_control_command(socket_path: &Path, command: &ControlCommand) -> Result<()> {
+    let mut stream = UnixStream::connect(socket_path).await.with_context(|| {
+        format!(
+            "Failed to connect to control socket at {}; is the daemon running?",
+            socket_path.display()
+        )
+    })?;
+
+    let line = match command {
+        ControlCommand::Next => "next".to_string(),
+        ControlCommand::Set { path } => format!("set {}", path.display()),
+        ControlCommand::Pause => "pause".to_string(),
+        ControlCommand::Resume => "resume".to_string(),
+        ControlCommand::ReapplyLast => "reapply-last".to_string(),
+        ControlCommand::Status => "status".to_string(),
+    };
+
+    stream
+        .write_all(format!("{}\n", line).as_bytes())
+        .await
+        .context("Failed to send control command")?;
+
+    let mut response = String::new();
+    BufReader::new(stream)
+        .read_line(&mut response)
+        .await
+        .context("Failed to read control response")?;
+
+    print!("{}", response);
+    Ok(())
+}
+
+/// Accept control connections for the lifetime of the daemon, handing each
+/// off to its own task.
+async fn run_control_socket(
+    socket_path: PathBuf,
+    cmd_tx: mpsc::UnboundedSender<DaemonCommand>,
+    images: Arc<Vec<PathBuf>>,
+    shared: Arc<SharedDaemonState>,
+) -> Result<()> {
+    if let Some(parent) = socket_path.parent() {
+        std::fs::create_dir_all(parent).ok();
+    }
+
+    // Remove a stale socket left behind by a previous run, if any.
+    match std::fs::remove_file(&socket_path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e).context("Failed to remove stale control socket"),
+    }
+
+    let listener = UnixListener::bind(&socket_path)
+        .with_context(|| format!("Failed to bind control socket: {}", socket_path.display()))?;
+
+    println!("Control socket listening at {}", socket_path.display());
+
+    loop {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .context("Failed to accept control connection")?;
+
+        let cmd_tx = cmd_tx.clone();
+        let images = Arc::clone(&images);
+        let shared = Arc::clone(&shared);
+
+        tokio::spawn(async move {
+            if let Err(e) = handle_control_connection(stream, cmd_tx, images, shared).await {
+                eprintln!("Warning: control connection error: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_control_connection(
+    mut stream: UnixStream,
+    cmd_tx: mpsc::UnboundedSender<DaemonCommand>,
+    images: Arc<Vec<PathBuf>>,
+    shared: Arc<SharedDaemonState>,
+) -> Result<()> {
+    let (reader, mut writer) = stream.split();
+    let mut line = String::new();
+    BufReader::new(reader)
+        .read_line(&mut line)
+        .await
+        .context("Failed to read control command")?;
+
+    let response = match SocketCommand::parse_line(&line) {
+        Ok(SocketCommand::Status) => {
+            let paused = shared.paused.load(Ordering::Relaxed);
+            let next_change_in = shared
+                .next_change_at
+                .lock()
+                .unwrap()
+                .map(|deadline| deadline.saturating_duration_since(tokio::time::Instant::now()));
+            let current = get_current_wallpaper().ok().flatten();
+
+            format!(
+                "ok paused={} pool_size={} current={} next_change_in_secs={}\n",
+                paused,
+                images.len(),
+                current
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                next_change_in
+                    .map(|duration| duration.as_secs().to_string())
+                    .unwrap_or_else(|| "n/a".to_string()),
+            )
+        }
+        Ok(SocketCommand::Set { path }) if !images.contains(&path) => {
+            format!(
+                "error: {} is not in the discovered image pool\n",
+                path.display()
+            )
+        }
+        Ok(command) => {
+            let daemon_command = match command {
+                SocketCommand::Next => DaemonCommand::Next,
+                SocketCommand::Set { path } => DaemonCommand::Set(path),
+                SocketCommand::Pause => DaemonCommand::Pause,
+                SocketCommand::Resume => DaemonCommand::Resume,
+                SocketCommand::ReapplyLast => DaemonCommand::ReapplyLast,
+                SocketCommand::Status => unreachable!("status is handled above"),
+            };
+
+            match cmd_tx.send(daemon_command) {
+                Ok(()) => "ok\n".to_string(),
+                Err(_) => "error: daemon loop is no longer running\n".to_string(),
+            }
+        }
+        Err(e) => format!("error: {}\n", e),
+    };
+
+    writer
+        .write_all(response.as_bytes())
+        .await
+        .context("Failed to write control response")?;
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    let socket_path = resolve_socket_path(cli.socket_path.as_ref())?;
+
+    if let Some(command) = &cli.command {
+        return send_control_command(&socket_path, command).await;
+    }
+
+    if cli.image_paths.is_empty() {
+        anyhow::bail!("--image-paths is required when not sending a control command");
+    }
+
     // Discover all available images
-    let images = discover_images(&cli.image_paths)?;
+    let mut images = discover_images(&cli.image_paths, &cli)?;
 
     if images.is_empty() {
         anyhow::bail!("No images found in the specified paths");
     }
 
+    if cli.dedupe {
+        images = dedupe_images(images, cli.dedupe_threshold)?;
+    }
+
     println!("Starting wallpaper switcher with {} images", images.len());
-    println!("Changing wallpaper every {} seconds", cli.interval_in_secs);
+
+    let schedule = if cli.time_based {
+        let schedule = build_schedule(&images, cli.schedule.as_ref())?;
+        println!(
+            "Time-based mode enabled with {} scheduled slots",
+            schedule.len()
+        );
+        Some(schedule)
+    } else {
+        println!("Changing wallpaper every {} seconds", cli.interval_in_secs);
+        None
+    };
+
+    let images = Arc::new(images);
 
     // Create SIGUSR1 signal listener
     let mut sigusr1_stream = signal(SignalKind::user_defined1())
         .context("Failed to register SIGUSR1 handler")?;
 
-    // Do an initial change once at startup
-    change_wallpaper_once(&images, &cli);
+    let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel::<DaemonCommand>();
+    let shared = Arc::new(SharedDaemonState {
+        paused: AtomicBool::new(false),
+        next_change_at: StdMutex::new(None),
+    });
+
+    let socket_images = Arc::clone(&images);
+    let socket_shared = Arc::clone(&shared);
+    tokio::spawn(async move {
+        if let Err(e) = run_control_socket(socket_path, cmd_tx, socket_images, socket_shared).await
+        {
+            eprintln!("Warning: control socket stopped: {}", e);
+        }
+    });
+
+    let mut history: VecDeque<PathBuf> = VecDeque::new();
+
+    // Do an initial change once at startup: reapply the persisted last
+    // wallpaper if requested, otherwise pick one as usual. Not supported in
+    // --per-monitor mode since the persisted path is only ever one output's
+    // wallpaper, not a set spanning every output.
+    if cli.reapply_last && cli.per_monitor {
+        eprintln!("Warning: --reapply-last is not supported with --per-monitor, ignoring it");
+    }
+
+    let reapplied = cli.reapply_last
+        && !cli.per_monitor
+        && match load_last_wallpaper() {
+            Some(path) if path.exists() => {
+                println!("Reapplying last wallpaper: {}", path.display());
+                match set_wallpaper(&path, None, &cli) {
+                    Ok(()) => {
+                        remember_shown(&mut history, path, cli.no_repeat_last);
+                        true
+                    }
+                    Err(e) => {
+                        eprintln!("Warning: Could not reapply last wallpaper: {}", e);
+                        false
+                    }
+                }
+            }
+            _ => {
+                println!("No persisted wallpaper to reapply; picking a new one");
+                false
+            }
+        };
+
+    if !reapplied {
+        change_wallpaper_once(&images, schedule.as_ref(), &mut history, &cli);
+    }
+
+    let mut paused = false;
 
-    // Main event loop: wait for either interval or SIGUSR1, then change wallpaper
+    // Main event loop: wait for the next change, SIGUSR1, or a control command
     loop {
-        println!(
-            "Waiting {} seconds until next change... (send SIGUSR1 to change immediately)",
-            cli.interval_in_secs
-        );
+        let wait = if paused {
+            None
+        } else {
+            Some(match &schedule {
+                Some(schedule) => next_slot_boundary(schedule, Local::now().time()),
+                None => Duration::from_secs(cli.interval_in_secs),
+            })
+        };
 
-        let sleep_fut = sleep(Duration::from_secs(cli.interval_in_secs));
+        shared.paused.store(paused, Ordering::Relaxed);
+        *shared.next_change_at.lock().unwrap() =
+            wait.map(|wait| tokio::time::Instant::now() + wait);
+
+        match wait {
+            Some(wait) => println!(
+                "Waiting {} seconds until next change... (send SIGUSR1 to change immediately)",
+                wait.as_secs()
+            ),
+            None => println!("Paused; waiting for a resume/next/set control command..."),
+        }
+
+        // Placeholder sleep while paused so the select below always has a
+        // pinned future to poll; disabled via the branch's `if !paused` guard.
+        let sleep_fut = sleep(wait.unwrap_or(Duration::from_secs(315_360_000)));
         tokio::pin!(sleep_fut);
 
-        tokio::select! {
-            _ = &mut sleep_fut => {
+        let forced_image = tokio::select! {
+            _ = &mut sleep_fut, if !paused => {
                 println!("Interval expired, changing wallpaper...");
+                None
             }
             _ = sigusr1_stream.recv() => {
                 println!("Received SIGUSR1 signal, changing wallpaper immediately...");
+                None
+            }
+            Some(command) = cmd_rx.recv() => {
+                match command {
+                    DaemonCommand::Next => {
+                        println!("Received control command: next");
+                        None
+                    }
+                    DaemonCommand::Set(path) => {
+                        if cli.per_monitor {
+                            eprintln!(
+                                "Warning: set is not supported with --per-monitor, ignoring it"
+                            );
+                            continue;
+                        } else if images.contains(&path) {
+                            println!("Received control command: set {}", path.display());
+                            Some(path)
+                        } else {
+                            eprintln!(
+                                "Warning: set command referenced an image not in the pool: {}",
+                                path.display()
+                            );
+                            continue;
+                        }
+                    }
+                    DaemonCommand::Pause => {
+                        println!("Received control command: pause");
+                        paused = true;
+                        continue;
+                    }
+                    DaemonCommand::Resume => {
+                        println!("Received control command: resume");
+                        paused = false;
+                        continue;
+                    }
+                    DaemonCommand::ReapplyLast if cli.per_monitor => {
+                        eprintln!(
+                            "Warning: reapply-last is not supported with --per-monitor, ignoring it"
+                        );
+                        continue;
+                    }
+                    DaemonCommand::ReapplyLast => match load_last_wallpaper() {
+                        Some(path) if path.exists() => {
+                            println!(
+                                "Received control command: reapply-last -> {}",
+                                path.display()
+                            );
+                            Some(path)
+                        }
+                        Some(path) => {
+                            eprintln!(
+                                "Warning: persisted wallpaper no longer exists: {}",
+                                path.display()
+                            );
+                            continue;
+                        }
+                        None => {
+                            eprintln!(
+                                "Warning: reapply-last requested but no wallpaper has been persisted yet"
+                            );
+                            continue;
+                        }
+                    },
+                }
             }
+        };
+
+        match forced_image {
+            Some(path) => {
+                if let Err(e) = set_wallpaper(&path, None, &cli) {
+                    eprintln!("Error setting wallpaper: {}", e);
+                } else {
+                    remember_shown(&mut history, path, cli.no_repeat_last);
+                }
+            }
+            None => change_wallpaper_once(&images, schedule.as_ref(), &mut history, &cli),
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule_fixture() -> Schedule {
+        vec![
+            (NaiveTime::from_hms_opt(6, 0, 0).unwrap(), PathBuf::from("morning.jpg")),
+            (NaiveTime::from_hms_opt(12, 0, 0).unwrap(), PathBuf::from("noon.jpg")),
+            (NaiveTime::from_hms_opt(20, 0, 0).unwrap(), PathBuf::from("evening.jpg")),
+        ]
+    }
+
+    #[test]
+    fn image_for_time_picks_current_slot() {
+        let schedule = schedule_fixture();
+        assert_eq!(
+            image_for_time(&schedule, NaiveTime::from_hms_opt(13, 30, 0).unwrap()),
+            Some(PathBuf::from("noon.jpg"))
+        );
+    }
+
+    #[test]
+    fn image_for_time_wraps_to_last_slot_before_first() {
+        let schedule = schedule_fixture();
+        assert_eq!(
+            image_for_time(&schedule, NaiveTime::from_hms_opt(2, 0, 0).unwrap()),
+            Some(PathBuf::from("evening.jpg"))
+        );
+    }
+
+    #[test]
+    fn next_slot_boundary_within_day() {
+        let schedule = schedule_fixture();
+        let wait = next_slot_boundary(&schedule, NaiveTime::from_hms_opt(6, 0, 0).unwrap());
+        assert_eq!(wait, Duration::from_secs(6 * 60 * 60));
+    }
+
+    #[test]
+    fn next_slot_boundary_wraps_to_next_day() {
+        let schedule = schedule_fixture();
+        let wait = next_slot_boundary(&schedule, NaiveTime::from_hms_opt(23, 0, 0).unwrap());
+        assert_eq!(wait, Duration::from_secs(7 * 60 * 60));
+    }
+
+    #[test]
+    fn hamming_distance_identical_hashes_is_zero() {
+        assert_eq!(hamming_distance(0xABCD, 0xABCD), 0);
+    }
+
+    #[test]
+    fn hamming_distance_counts_differing_bits() {
+        assert_eq!(hamming_distance(0b1010, 0b0010), 1);
+        assert_eq!(hamming_distance(0b1111, 0b0000), 4);
+    }
+
+    fn fingerprint(hash: Option<u64>, width: u32, height: u32) -> ImageFingerprint {
+        ImageFingerprint {
+            mtime_secs: 0,
+            hash,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn group_by_hash_merges_within_threshold_keeping_highest_resolution() {
+        let fingerprints = vec![
+            (PathBuf::from("small.jpg"), fingerprint(Some(0b0000), 100, 100)),
+            (PathBuf::from("large.jpg"), fingerprint(Some(0b0001), 400, 400)),
+        ];
+        let kept = group_by_hash(fingerprints, 2);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].0, PathBuf::from("large.jpg"));
+    }
+
+    #[test]
+    fn group_by_hash_threshold_zero_still_merges_exact_matches() {
+        let fingerprints = vec![
+            (PathBuf::from("a.jpg"), fingerprint(Some(0b1010), 100, 100)),
+            (PathBuf::from("b.jpg"), fingerprint(Some(0b1010), 200, 200)),
+        ];
+        let kept = group_by_hash(fingerprints, 0);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].0, PathBuf::from("b.jpg"));
+    }
+
+    #[test]
+    fn group_by_hash_keeps_unhashable_images_as_singletons() {
+        let fingerprints = vec![
+            (PathBuf::from("a.jpg"), fingerprint(Some(0b1010), 100, 100)),
+            (PathBuf::from("corrupt.jpg"), fingerprint(None, 0, 0)),
+            (PathBuf::from("b.jpg"), fingerprint(None, 0, 0)),
+        ];
+        let kept = group_by_hash(fingerprints, 4);
+        assert_eq!(kept.len(), 3);
+    }
+
+    #[test]
+    fn parse_line_plain_text_commands() {
+        assert!(matches!(
+            SocketCommand::parse_line("next").unwrap(),
+            SocketCommand::Next
+        ));
+        assert!(matches!(
+            SocketCommand::parse_line("pause").unwrap(),
+            SocketCommand::Pause
+        ));
+        assert!(matches!(
+            SocketCommand::parse_line("resume").unwrap(),
+            SocketCommand::Resume
+        ));
+        assert!(matches!(
+            SocketCommand::parse_line("reapply-last").unwrap(),
+            SocketCommand::ReapplyLast
+        ));
+        assert!(matches!(
+            SocketCommand::parse_line("status").unwrap(),
+            SocketCommand::Status
+        ));
+    }
+
+    #[test]
+    fn parse_line_set_with_path() {
+        match SocketCommand::parse_line("set /tmp/foo.png").unwrap() {
+            SocketCommand::Set { path } => assert_eq!(path, PathBuf::from("/tmp/foo.png")),
+            other => panic!("expected Set, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_line_set_without_path_is_an_error() {
+        assert!(SocketCommand::parse_line("set").is_err());
+        assert!(SocketCommand::parse_line("set   ").is_err());
+    }
+
+    #[test]
+    fn parse_line_json_command() {
+        match SocketCommand::parse_line(r#"{"cmd":"set","path":"/tmp/bar.png"}"#).unwrap() {
+            SocketCommand::Set { path } => assert_eq!(path, PathBuf::from("/tmp/bar.png")),
+            other => panic!("expected Set, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_line_unknown_command_is_an_error() {
+        assert!(SocketCommand::parse_line("frobnicate").is_err());
+    }
+
+    #[test]
+    fn remember_shown_trims_to_capacity() {
+        let mut history = VecDeque::new();
+        remember_shown(&mut history, PathBuf::from("a.jpg"), 2);
+        remember_shown(&mut history, PathBuf::from("b.jpg"), 2);
+        remember_shown(&mut history, PathBuf::from("c.jpg"), 2);
+        assert_eq!(
+            history,
+            VecDeque::from([PathBuf::from("b.jpg"), PathBuf::from("c.jpg")])
+        );
+    }
+
+    #[test]
+    fn remember_shown_zero_capacity_disables_history() {
+        let mut history = VecDeque::new();
+        remember_shown(&mut history, PathBuf::from("a.jpg"), 0);
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn select_random_image_excluding_avoids_excluded_entries() {
+        let images = vec![PathBuf::from("a.jpg"), PathBuf::from("b.jpg")];
+        let excluded = PathBuf::from("a.jpg");
+        for _ in 0..20 {
+            let picked = select_random_image_excluding(&images, &[&excluded]).unwrap();
+            assert_eq!(picked, PathBuf::from("b.jpg"));
+        }
+    }
 
-        change_wallpaper_once(&images, &cli);
+    #[test]
+    fn select_random_image_excluding_falls_back_when_everything_excluded() {
+        let images = vec![PathBuf::from("a.jpg"), PathBuf::from("b.jpg")];
+        let a = PathBuf::from("a.jpg");
+        let b = PathBuf::from("b.jpg");
+        let picked = select_random_image_excluding(&images, &[&a, &b]);
+        assert!(picked.is_some());
     }
 }